@@ -4,6 +4,7 @@ use std::{
     borrow::{Borrow, Cow},
     fmt, mem,
     ops::Deref,
+    simd::{LaneCount, SupportedLaneCount},
     simd::prelude::*,
 };
 
@@ -18,6 +19,10 @@ pub struct Mutf8String {
     vec: Vec<u8>,
 }
 
+/// Checks whether every byte in `slice` is a "plain" byte, i.e. `0 < b < 0x80`. Such a slice is
+/// both valid MUTF-8 and valid UTF-8 with an identical encoding, since real MUTF-8 never contains
+/// a raw `0x00` (it's always encoded as the two bytes `0xC0 0x80`) and never sets the high bit
+/// outside of a multi-byte sequence.
 #[inline]
 fn is_plain_ascii(slice: &[u8]) -> bool {
     let mut is_plain_ascii = true;
@@ -26,48 +31,32 @@ fn is_plain_ascii(slice: &[u8]) -> bool {
     if remainder.len() > 16 {
         let chunk;
         (chunk, remainder) = remainder.split_array_ref::<16>();
-        let mask = u8x16::splat(0b10000000);
-        let zero = u8x16::splat(0);
-        let simd = u8x16::from_array(*chunk);
-        let xor = simd & mask;
-        if xor != zero {
+        if !is_plain_ascii_chunk(u8x16::from_array(*chunk)) {
             is_plain_ascii = false;
         }
     }
     if remainder.len() > 8 {
         let chunk;
         (chunk, remainder) = remainder.split_array_ref::<8>();
-        let mask = u8x8::splat(0b10000000);
-        let zero = u8x8::splat(0);
-        let simd = u8x8::from_array(*chunk);
-        let xor = simd & mask;
-        if xor != zero {
+        if !is_plain_ascii_chunk(u8x8::from_array(*chunk)) {
             is_plain_ascii = false;
         }
     }
     if remainder.len() > 4 {
         let chunk;
         (chunk, remainder) = remainder.split_array_ref::<4>();
-        let mask = u8x4::splat(0b10000000);
-        let zero = u8x4::splat(0);
-        let simd = u8x4::from_array(*chunk);
-        let xor = simd & mask;
-        if xor != zero {
+        if !is_plain_ascii_chunk(u8x4::from_array(*chunk)) {
             is_plain_ascii = false;
         }
     }
     for &byte in remainder {
-        if byte & 0b10000000 != 0 {
+        if byte == 0 || byte & 0b10000000 != 0 {
             is_plain_ascii = false;
         }
     }
 
     for &chunk in chunks_32_exact {
-        let mask = u8x32::splat(0b10000000);
-        let zero = u8x32::splat(0);
-        let simd = u8x32::from_array(chunk);
-        let xor = simd & mask;
-        if xor != zero {
+        if !is_plain_ascii_chunk(u8x32::from_array(chunk)) {
             is_plain_ascii = false;
         }
     }
@@ -75,6 +64,17 @@ fn is_plain_ascii(slice: &[u8]) -> bool {
     is_plain_ascii
 }
 
+/// Checks that every lane of `simd` satisfies `0 < b < 0x80`.
+#[inline]
+fn is_plain_ascii_chunk<const N: usize>(simd: Simd<u8, N>) -> bool
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let high_bit = Simd::splat(0b10000000u8);
+    let zero = Simd::splat(0u8);
+    (simd & high_bit).simd_eq(zero).all() && simd.simd_ne(zero).all()
+}
+
 impl Mutf8Str {
     #[inline]
     pub fn to_string_lossy(&self) -> Cow<str> {
@@ -91,7 +91,7 @@ impl Mutf8Str {
     #[allow(clippy::should_implement_trait)]
     #[inline]
     pub fn from_str(s: &str) -> Cow<Mutf8Str> {
-        match mutf8::encode(s) {
+        match encode(s) {
             Cow::Borrowed(b) => Cow::Borrowed(Mutf8Str::from_slice(b)),
             Cow::Owned(o) => Cow::Owned(Mutf8String { vec: o }),
         }
@@ -104,10 +104,7 @@ impl Mutf8Str {
             // SAFETY: &[u8] and &str are the same layout.
             unsafe { Cow::Borrowed(std::str::from_utf8_unchecked(&self.slice)) }
         } else {
-            match mutf8::decode(&self.slice).expect("Mutf8Str must alwaus be valid MUTF-8") {
-                Cow::Borrowed(b) => Cow::Borrowed(b),
-                Cow::Owned(o) => Cow::Owned(o),
-            }
+            decode(&self.slice)
         }
     }
 
@@ -157,7 +154,7 @@ impl Mutf8String {
             // SAFETY: &[u8] and &str are the same layout.
             unsafe { String::from_utf8_unchecked(self.vec) }
         } else {
-            match mutf8::decode(&self.vec).expect("Mutf8Str must alwaus be valid MUTF-8") {
+            match decode(&self.vec) {
                 Cow::Borrowed(b) => b.to_owned(),
                 Cow::Owned(o) => o,
             }
@@ -178,7 +175,92 @@ impl Deref for Mutf8String {
     }
 }
 
-// TODO: make Mutf8 correct
+/// Decodes MUTF-8 bytes (known to already be well-formed, as produced by the game or by
+/// [`encode`]) into UTF-8. MUTF-8 only differs from UTF-8 in two ways: `U+0000` is stored as the
+/// two bytes `0xC0 0x80`, and codepoints above `U+FFFF` are split into a pair of 3-byte CESU-8
+/// surrogate halves instead of one 4-byte UTF-8 sequence. Everything else is copied as-is.
+fn decode(bytes: &[u8]) -> Cow<str> {
+    if !bytes.contains(&0xC0) && !bytes.contains(&0xED) {
+        // no null bytes and no surrogate pairs, so this is already plain UTF-8.
+        // SAFETY: Mutf8Str is always valid MUTF-8, and without those two special cases MUTF-8 and
+        // UTF-8 are byte-for-byte identical.
+        return Cow::Borrowed(unsafe { std::str::from_utf8_unchecked(bytes) });
+    }
+
+    let mut out = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i..] {
+            [0xC0, 0x80, ..] => {
+                out.push('\0');
+                i += 2;
+            }
+            [0xED, b2 @ 0xA0..=0xAF, b3, 0xED, b5 @ 0xB0..=0xBF, b6, ..] => {
+                let high = 0xD000u32 | ((b2 as u32 & 0x3F) << 6) | (b3 as u32 & 0x3F);
+                let low = 0xD000u32 | ((b5 as u32 & 0x3F) << 6) | (b6 as u32 & 0x3F);
+                let codepoint = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                out.push(char::from_u32(codepoint).unwrap_or('\u{FFFD}'));
+                i += 6;
+            }
+            _ => {
+                let len = utf8_sequence_len(bytes[i]);
+                let end = (i + len).min(bytes.len());
+                out.push_str(std::str::from_utf8(&bytes[i..end]).unwrap_or("\u{FFFD}"));
+                i = end;
+            }
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Encodes UTF-8 into MUTF-8, rewriting `U+0000` as `0xC0 0x80` and splitting codepoints above
+/// `U+FFFF` into a pair of 3-byte CESU-8 surrogate halves.
+fn encode(s: &str) -> Cow<[u8]> {
+    if !s.as_bytes().contains(&0) && s.chars().all(|c| (c as u32) <= 0xFFFF) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+
+    let mut out = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        let codepoint = c as u32;
+        if codepoint == 0 {
+            out.extend_from_slice(&[0xC0, 0x80]);
+        } else if codepoint > 0xFFFF {
+            let reduced = codepoint - 0x10000;
+            let high = 0xD800 + (reduced >> 10);
+            let low = 0xDC00 + (reduced & 0x3FF);
+            push_cesu8_surrogate(&mut out, high);
+            push_cesu8_surrogate(&mut out, low);
+        } else {
+            let mut buf = [0u8; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Encodes a UTF-16 surrogate code unit (`0xD800..=0xDFFF`) as the 3-byte sequence CESU-8 uses in
+/// place of one half of a 4-byte UTF-8 astral character.
+#[inline]
+fn push_cesu8_surrogate(out: &mut Vec<u8>, unit: u32) {
+    out.push(0xE0 | ((unit >> 12) & 0x0F) as u8);
+    out.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+    out.push(0x80 | (unit & 0x3F) as u8);
+}
+
+/// The length, in bytes, of the UTF-8 sequence starting with this leading byte.
+#[inline]
+fn utf8_sequence_len(leading_byte: u8) -> usize {
+    if leading_byte & 0b1000_0000 == 0 {
+        1
+    } else if leading_byte & 0b1110_0000 == 0b1100_0000 {
+        2
+    } else if leading_byte & 0b1111_0000 == 0b1110_0000 {
+        3
+    } else {
+        4
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -219,4 +301,16 @@ mod tests {
             Cow::Borrowed(str)
         );
     }
+
+    #[test]
+    fn raw_null_byte_is_not_treated_as_plain_ascii() {
+        // a raw 0x00 never appears in real MUTF-8 (it's always `0xC0 0x80`), but make sure the
+        // fast path doesn't misinterpret one as a plain borrowable byte if it ever shows up.
+        let mutf8_data = &[b'a', 0x00, b'b'];
+        assert!(!super::is_plain_ascii(mutf8_data));
+        assert_eq!(
+            Mutf8Str::from_slice(mutf8_data).to_str(),
+            Cow::Borrowed("a\0b")
+        );
+    }
 }
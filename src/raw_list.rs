@@ -0,0 +1,199 @@
+//! A reference to a big-endian NBT int/long array that's materialized into a native-endian
+//! `Vec` lazily, on demand, via [`RawList::to_vec`].
+
+use std::marker::PhantomData;
+#[cfg(target_endian = "little")]
+use std::simd::prelude::*;
+
+/// A type that can be read out of a raw big-endian NBT array.
+pub trait RawListType: Sized + Copy {
+    /// The width of one element, in bytes.
+    const SIZE: usize;
+
+    fn from_be_bytes(bytes: &[u8]) -> Self;
+}
+
+impl RawListType for i32 {
+    const SIZE: usize = 4;
+
+    #[inline]
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        i32::from_be_bytes(bytes.try_into().unwrap())
+    }
+}
+impl RawListType for i64 {
+    const SIZE: usize = 8;
+
+    #[inline]
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        i64::from_be_bytes(bytes.try_into().unwrap())
+    }
+}
+
+/// A reference to the raw bytes of an NBT `IntArray`/`LongArray`, kept in big-endian order until
+/// [`to_vec`](RawList::to_vec) is called. This avoids paying the byte-swap cost for arrays that
+/// are never read.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RawList<'a, T> {
+    data: &'a [u8],
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T: RawListType> RawList<'a, T> {
+    #[inline]
+    pub fn new(data: &'a [u8]) -> Self {
+        RawList {
+            data,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len() / T::SIZE
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl<'a> RawList<'a, i32> {
+    /// Converts the raw big-endian bytes into a `Vec<i32>` in native endianness.
+    pub fn to_vec(&self) -> Vec<i32> {
+        swap_endianness_i32(self.data)
+    }
+}
+impl<'a> RawList<'a, i64> {
+    /// Converts the raw big-endian bytes into a `Vec<i64>` in native endianness.
+    pub fn to_vec(&self) -> Vec<i64> {
+        swap_endianness_i64(self.data)
+    }
+}
+
+/// Byte-swaps a slice of big-endian `i32`s into a native-endian `Vec<i32>`.
+///
+/// On little-endian hosts this is done 8 elements (32 bytes) at a time using SIMD: each 32-byte
+/// chunk is loaded as a `u8x32` and swizzled so every 4-byte lane is internally reversed, then
+/// each of the eight 4-byte lanes is read out with `i32::from_ne_bytes` and pushed; the vector is
+/// not reinterpreted as an `i32x8`. The trailing `len % 8` elements are converted the plain scalar
+/// way.
+fn swap_endianness_i32(data: &[u8]) -> Vec<i32> {
+    let len = data.len() / 4;
+
+    #[cfg(target_endian = "little")]
+    {
+        let mut out = Vec::with_capacity(len);
+        let chunks = data.chunks_exact(32);
+        let remainder = chunks.remainder();
+
+        // reverses each of the eight 4-byte lanes within a 32-byte (8-lane) register
+        const SWIZZLE: [u8; 32] = [
+            3, 2, 1, 0, 7, 6, 5, 4, 11, 10, 9, 8, 15, 14, 13, 12, //
+            19, 18, 17, 16, 23, 22, 21, 20, 27, 26, 25, 24, 31, 30, 29, 28,
+        ];
+        let swizzle = u8x32::from_array(SWIZZLE);
+
+        for chunk in chunks {
+            let bytes = u8x32::from_slice(chunk);
+            let swapped = bytes.swizzle_dyn(swizzle);
+            for lane in swapped.to_array().as_chunks::<4>().0 {
+                out.push(i32::from_ne_bytes(*lane));
+            }
+        }
+
+        for bytes in remainder.chunks_exact(4) {
+            out.push(i32::from_be_bytes(bytes.try_into().unwrap()));
+        }
+
+        out
+    }
+
+    #[cfg(not(target_endian = "little"))]
+    {
+        data.chunks_exact(4)
+            .map(|bytes| i32::from_be_bytes(bytes.try_into().unwrap()))
+            .collect()
+    }
+}
+
+/// Byte-swaps a slice of big-endian `i64`s into a native-endian `Vec<i64>`.
+///
+/// Same approach as [`swap_endianness_i32`], but each 32-byte register holds 4 lanes of 8 bytes.
+fn swap_endianness_i64(data: &[u8]) -> Vec<i64> {
+    #[cfg(target_endian = "little")]
+    {
+        let len = data.len() / 8;
+        let mut out = Vec::with_capacity(len);
+        let chunks = data.chunks_exact(32);
+        let remainder = chunks.remainder();
+
+        // reverses each of the four 8-byte lanes within a 32-byte (4-lane) register
+        const SWIZZLE: [u8; 32] = [
+            7, 6, 5, 4, 3, 2, 1, 0, 15, 14, 13, 12, 11, 10, 9, 8, //
+            23, 22, 21, 20, 19, 18, 17, 16, 31, 30, 29, 28, 27, 26, 25, 24,
+        ];
+        let swizzle = u8x32::from_array(SWIZZLE);
+
+        for chunk in chunks {
+            let bytes = u8x32::from_slice(chunk);
+            let swapped = bytes.swizzle_dyn(swizzle);
+            for lane in swapped.to_array().as_chunks::<8>().0 {
+                out.push(i64::from_ne_bytes(*lane));
+            }
+        }
+
+        for bytes in remainder.chunks_exact(8) {
+            out.push(i64::from_be_bytes(bytes.try_into().unwrap()));
+        }
+
+        out
+    }
+
+    #[cfg(not(target_endian = "little"))]
+    {
+        data.chunks_exact(8)
+            .map(|bytes| i64::from_be_bytes(bytes.try_into().unwrap()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{WriteBytesExt, BE};
+
+    use super::*;
+
+    #[test]
+    fn swaps_int_array() {
+        let mut data = Vec::new();
+        for i in 0..20 {
+            data.write_i32::<BE>(i).unwrap();
+        }
+        let list = RawList::<i32>::new(&data);
+        assert_eq!(list.len(), 20);
+        assert_eq!(list.to_vec(), (0..20).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn swaps_long_array() {
+        let mut data = Vec::new();
+        for i in 0..20 {
+            data.write_i64::<BE>(i).unwrap();
+        }
+        let list = RawList::<i64>::new(&data);
+        assert_eq!(list.len(), 20);
+        assert_eq!(list.to_vec(), (0..20).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn swaps_non_multiple_of_8_int_array() {
+        let mut data = Vec::new();
+        for i in 0..13 {
+            data.write_i32::<BE>(i).unwrap();
+        }
+        let list = RawList::<i32>::new(&data);
+        assert_eq!(list.to_vec(), (0..13).collect::<Vec<i32>>());
+    }
+}
@@ -0,0 +1,531 @@
+//! SNBT (stringified NBT) parsing and writing. This is the human-readable text format used by
+//! Minecraft commands, data packs, and `/data get`/`/data merge`, e.g.
+//! `{name:"Bananrama",count:3b,list:[1,2,3],arr:[I;1,2,3]}`.
+//!
+//! [`Tag::from_snbt`] parses a string into the owned tag tree, and [`Tag::to_snbt`] (along with
+//! the equivalents on [`CompoundTag`] and [`ListTag`]) writes it back out.
+
+use std::fmt::{self, Write as _};
+
+use crate::{
+    owned::{CompoundTag, ListTag, Tag},
+    Mutf8Str, Mutf8String,
+};
+
+/// An error that occurred while parsing SNBT.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SnbtError {
+    /// The input ended before the value was complete.
+    UnexpectedEof,
+    /// A character was found that doesn't belong where it was found.
+    UnexpectedChar(char),
+    /// A number couldn't be parsed, e.g. `300b` (out of range for its suffix) or a bare integer
+    /// too large for an `i32`.
+    InvalidNumber(String),
+    /// There was leftover input after a complete value was parsed.
+    TrailingData,
+}
+
+impl fmt::Display for SnbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnbtError::UnexpectedEof => write!(f, "unexpected end of input"),
+            SnbtError::UnexpectedChar(c) => write!(f, "unexpected character '{c}'"),
+            SnbtError::InvalidNumber(s) => write!(f, "invalid number '{s}'"),
+            SnbtError::TrailingData => write!(f, "trailing data after value"),
+        }
+    }
+}
+impl std::error::Error for SnbtError {}
+
+impl Tag {
+    /// Parses a single SNBT value, e.g. `{a:1,b:"hi"}` or `[1,2,3]` or `3.5d`.
+    pub fn from_snbt(s: &str) -> Result<Tag, SnbtError> {
+        let mut parser = Parser::new(s);
+        let tag = parser.parse_value()?;
+        parser.skip_whitespace();
+        if !parser.is_eof() {
+            return Err(SnbtError::TrailingData);
+        }
+        Ok(tag)
+    }
+
+    /// Writes this tag in SNBT form.
+    pub fn to_snbt(&self) -> String {
+        let mut out = String::new();
+        write_tag(&mut out, self);
+        out
+    }
+}
+
+impl CompoundTag {
+    /// Parses an SNBT compound, e.g. `{a:1,b:"hi"}`.
+    pub fn from_snbt(s: &str) -> Result<CompoundTag, SnbtError> {
+        match Tag::from_snbt(s)? {
+            Tag::Compound(compound) => Ok(compound),
+            _ => Err(SnbtError::UnexpectedChar('{')),
+        }
+    }
+
+    /// Writes this compound in SNBT form.
+    pub fn to_snbt(&self) -> String {
+        let mut out = String::new();
+        write_compound(&mut out, self);
+        out
+    }
+}
+
+impl ListTag {
+    /// Writes this list in SNBT form.
+    pub fn to_snbt(&self) -> String {
+        let mut out = String::new();
+        write_list(&mut out, self);
+        out
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    src: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Parser {
+            chars: src.char_indices().peekable(),
+            src,
+        }
+    }
+
+    fn is_eof(&mut self) -> bool {
+        self.chars.peek().is_none()
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next().map(|(_, c)| c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SnbtError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(SnbtError::UnexpectedChar(c)),
+            None => Err(SnbtError::UnexpectedEof),
+        }
+    }
+
+    /// Parses from the current position to the end of the current unquoted token (a bare word,
+    /// like a key or a number).
+    fn parse_unquoted(&mut self) -> &'a str {
+        let start = self.chars.peek().map(|&(i, _)| i).unwrap_or(self.src.len());
+        while matches!(self.peek(), Some(c) if is_unquoted_char(c)) {
+            self.next();
+        }
+        let end = self.chars.peek().map(|&(i, _)| i).unwrap_or(self.src.len());
+        &self.src[start..end]
+    }
+
+    fn parse_value(&mut self) -> Result<Tag, SnbtError> {
+        self.skip_whitespace();
+        match self.peek().ok_or(SnbtError::UnexpectedEof)? {
+            '{' => self.parse_compound().map(Tag::Compound),
+            '[' => self.parse_list_or_array(),
+            '"' | '\'' => Ok(Tag::String(mutf8(&self.parse_quoted_string()?))),
+            _ => self.parse_unquoted_value(),
+        }
+    }
+
+    fn parse_compound(&mut self) -> Result<CompoundTag, SnbtError> {
+        self.expect('{')?;
+        let mut compound = CompoundTag::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(compound);
+        }
+        loop {
+            self.skip_whitespace();
+            let key = if matches!(self.peek(), Some('"') | Some('\'')) {
+                self.parse_quoted_string()?
+            } else {
+                self.parse_unquoted().to_string()
+            };
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            compound.insert(key, value);
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                Some(c) => return Err(SnbtError::UnexpectedChar(c)),
+                None => return Err(SnbtError::UnexpectedEof),
+            }
+        }
+        Ok(compound)
+    }
+
+    /// Parses either a typed array (`[B;...]`, `[I;...]`, `[L;...]`) or a regular list (`[...]`).
+    fn parse_list_or_array(&mut self) -> Result<Tag, SnbtError> {
+        self.expect('[')?;
+
+        // typed arrays have a single-letter prefix followed by `;`
+        let mut lookahead = self.chars.clone();
+        if let Some((_, prefix)) = lookahead.next() {
+            if let Some((_, ';')) = lookahead.next() {
+                self.next();
+                self.next();
+                return match prefix {
+                    'B' => self
+                        .parse_typed_array('b', |s| s.parse::<i8>().map(|v| v as u8))
+                        .map(Tag::ByteArray),
+                    'I' => self
+                        .parse_typed_array('i', |s| s.parse::<i32>())
+                        .map(Tag::IntArray),
+                    'L' => self
+                        .parse_typed_array('l', |s| s.parse::<i64>())
+                        .map(Tag::LongArray),
+                    c => Err(SnbtError::UnexpectedChar(c)),
+                };
+            }
+        }
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Tag::List(ListTag::Empty));
+        }
+        let mut values = Vec::new();
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => break,
+                Some(c) => return Err(SnbtError::UnexpectedChar(c)),
+                None => return Err(SnbtError::UnexpectedEof),
+            }
+        }
+        Ok(Tag::List(ListTag::from(values)))
+    }
+
+    /// Parses the comma-separated elements of a typed array (`[B;...]`/`[I;...]`/`[L;...]`)
+    /// directly as `T`, rather than through [`Parser::parse_value`] — a bare element like `1`
+    /// would parse to `Tag::Int` regardless of the array's element type, so the array's own
+    /// element type has to drive parsing instead of a generic `Tag` round trip. Each element may
+    /// optionally carry the matching type suffix (e.g. `1b` inside `[B;...]`).
+    fn parse_typed_array<T>(
+        &mut self,
+        suffix: char,
+        parse: impl Fn(&str) -> Result<T, std::num::ParseIntError>,
+    ) -> Result<Vec<T>, SnbtError> {
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(values);
+        }
+        loop {
+            self.skip_whitespace();
+            let token = self.parse_unquoted();
+            if token.is_empty() {
+                return Err(SnbtError::UnexpectedChar(self.peek().unwrap_or('\0')));
+            }
+            let digits = match token.chars().last() {
+                Some(c) if c.eq_ignore_ascii_case(&suffix) => &token[..token.len() - 1],
+                _ => token,
+            };
+            values.push(parse(digits).map_err(|_| SnbtError::InvalidNumber(token.to_string()))?);
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => break,
+                Some(c) => return Err(SnbtError::UnexpectedChar(c)),
+                None => return Err(SnbtError::UnexpectedEof),
+            }
+        }
+        Ok(values)
+    }
+
+    fn parse_quoted_string(&mut self) -> Result<String, SnbtError> {
+        let quote = self.next().ok_or(SnbtError::UnexpectedEof)?;
+        let mut out = String::new();
+        loop {
+            match self.next().ok_or(SnbtError::UnexpectedEof)? {
+                c if c == quote => break,
+                '\\' => match self.next().ok_or(SnbtError::UnexpectedEof)? {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Parses a bare (unquoted) token and figures out whether it's a number with a type suffix,
+    /// a bare number, or a string (e.g. `true`/`false`/an unquoted key used as a value).
+    fn parse_unquoted_value(&mut self) -> Result<Tag, SnbtError> {
+        let token = self.parse_unquoted();
+        if token.is_empty() {
+            return Err(SnbtError::UnexpectedChar(self.peek().unwrap_or('\0')));
+        }
+        parse_number_or_string(token)
+    }
+}
+
+/// Converts a UTF-8 string into an owned MUTF-8 string.
+fn mutf8(s: &str) -> Mutf8String {
+    Mutf8Str::from_str(s).into_owned()
+}
+
+fn is_unquoted_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+fn parse_number_or_string(token: &str) -> Result<Tag, SnbtError> {
+    // matches Minecraft's own SNBT parser, and the `visit_bool` impl in the serde module
+    if token.eq_ignore_ascii_case("true") {
+        return Ok(Tag::Byte(1));
+    }
+    if token.eq_ignore_ascii_case("false") {
+        return Ok(Tag::Byte(0));
+    }
+
+    let (digits, suffix) = match token.chars().last() {
+        Some(c @ ('b' | 'B' | 's' | 'S' | 'l' | 'L' | 'f' | 'F' | 'd' | 'D'))
+            if token[..token.len() - 1].parse::<f64>().is_ok() =>
+        {
+            (&token[..token.len() - 1], Some(c.to_ascii_lowercase()))
+        }
+        _ => (token, None),
+    };
+
+    match suffix {
+        Some('b') => digits
+            .parse::<i8>()
+            .map(Tag::Byte)
+            .map_err(|_| SnbtError::InvalidNumber(token.to_string())),
+        Some('s') => digits
+            .parse::<i16>()
+            .map(Tag::Short)
+            .map_err(|_| SnbtError::InvalidNumber(token.to_string())),
+        Some('l') => digits
+            .parse::<i64>()
+            .map(Tag::Long)
+            .map_err(|_| SnbtError::InvalidNumber(token.to_string())),
+        Some('f') => digits
+            .parse::<f32>()
+            .map(Tag::Float)
+            .map_err(|_| SnbtError::InvalidNumber(token.to_string())),
+        Some('d') => digits
+            .parse::<f64>()
+            .map(Tag::Double)
+            .map_err(|_| SnbtError::InvalidNumber(token.to_string())),
+        _ => {
+            if let Ok(i) = digits.parse::<i32>() {
+                Ok(Tag::Int(i))
+            } else if digits.contains('.') {
+                if let Ok(d) = digits.parse::<f64>() {
+                    return Ok(Tag::Double(d));
+                }
+                Ok(Tag::String(mutf8(token)))
+            } else if is_bare_integer(digits) {
+                // a bare integer too big for an i32 isn't silently widened to a long (there's no
+                // suffix to say that's what was meant) or treated as a string (it plainly isn't
+                // one) -- it's a deliberate parse error, same as an out-of-range `300b`.
+                Err(SnbtError::InvalidNumber(token.to_string()))
+            } else {
+                Ok(Tag::String(mutf8(token)))
+            }
+        }
+    }
+}
+
+/// Whether `s` is only an optional sign followed by one or more ASCII digits, i.e. it looks like
+/// an integer literal even though it might not fit in the type it's being parsed as.
+fn is_bare_integer(s: &str) -> bool {
+    let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn write_tag(out: &mut String, tag: &Tag) {
+    match tag {
+        Tag::Byte(v) => write!(out, "{v}b").unwrap(),
+        Tag::Short(v) => write!(out, "{v}s").unwrap(),
+        Tag::Int(v) => write!(out, "{v}").unwrap(),
+        Tag::Long(v) => write!(out, "{v}l").unwrap(),
+        Tag::Float(v) => write!(out, "{v}f").unwrap(),
+        Tag::Double(v) => write!(out, "{v}d").unwrap(),
+        // `Tag::ByteArray` stores bytes as `u8`, but SNBT (and the parser's `parse_typed_array`
+        // above) treats array elements as signed `i8`s with a `b` suffix, same as a bare `Byte` --
+        // writing the unsigned decimal here would round-trip `200` into a parse error instead of
+        // `-56b`.
+        Tag::ByteArray(arr) => {
+            write_typed_array(out, 'B', arr.iter().map(|v| format!("{}b", *v as i8)))
+        }
+        Tag::String(s) => write_quoted_string(out, &s.to_str()),
+        Tag::List(list) => write_list(out, list),
+        Tag::Compound(compound) => write_compound(out, compound),
+        Tag::IntArray(arr) => write_typed_array(out, 'I', arr.iter().map(|v| v.to_string())),
+        Tag::LongArray(arr) => write_typed_array(out, 'L', arr.iter().map(|v| format!("{v}l"))),
+    }
+}
+
+fn write_typed_array(out: &mut String, prefix: char, values: impl Iterator<Item = String>) {
+    out.push('[');
+    out.push(prefix);
+    out.push(';');
+    for (i, value) in values.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&value);
+    }
+    out.push(']');
+}
+
+fn write_list(out: &mut String, list: &ListTag) {
+    out.push('[');
+    for i in 0..list.len() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_tag(out, &list.get(i).expect("i is in bounds"));
+    }
+    out.push(']');
+}
+
+fn write_compound(out: &mut String, compound: &CompoundTag) {
+    out.push('{');
+    for (i, (key, value)) in compound.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_key(out, key);
+        out.push(':');
+        write_tag(out, value);
+    }
+    out.push('}');
+}
+
+fn write_key(out: &mut String, key: &Mutf8Str) {
+    let key = key.to_str();
+    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        out.push_str(&key);
+    } else {
+        write_quoted_string(out, &key);
+    }
+}
+
+fn write_quoted_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scalars() {
+        assert_eq!(Tag::from_snbt("3b").unwrap(), Tag::Byte(3));
+        assert_eq!(Tag::from_snbt("3s").unwrap(), Tag::Short(3));
+        assert_eq!(Tag::from_snbt("3").unwrap(), Tag::Int(3));
+        assert_eq!(Tag::from_snbt("3l").unwrap(), Tag::Long(3));
+        assert_eq!(Tag::from_snbt("3.5f").unwrap(), Tag::Float(3.5));
+        assert_eq!(Tag::from_snbt("3.5d").unwrap(), Tag::Double(3.5));
+        assert_eq!(Tag::from_snbt("3.5").unwrap(), Tag::Double(3.5));
+    }
+
+    #[test]
+    fn parses_booleans_as_bytes() {
+        assert_eq!(Tag::from_snbt("true").unwrap(), Tag::Byte(1));
+        assert_eq!(Tag::from_snbt("false").unwrap(), Tag::Byte(0));
+    }
+
+    #[test]
+    fn oversized_bare_integer_is_an_error() {
+        assert_eq!(
+            Tag::from_snbt("99999999999"),
+            Err(SnbtError::InvalidNumber("99999999999".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_strings() {
+        assert_eq!(
+            Tag::from_snbt("\"Bananrama\"").unwrap(),
+            Tag::String(mutf8("Bananrama"))
+        );
+        assert_eq!(Tag::from_snbt("hello").unwrap(), Tag::String(mutf8("hello")));
+    }
+
+    #[test]
+    fn parses_typed_arrays() {
+        assert_eq!(
+            Tag::from_snbt("[I;1,2,3]").unwrap(),
+            Tag::IntArray(vec![1, 2, 3])
+        );
+        assert_eq!(
+            Tag::from_snbt("[B;1,2,3]").unwrap(),
+            Tag::ByteArray(vec![1, 2, 3])
+        );
+        assert_eq!(
+            Tag::from_snbt("[L;1,2,3]").unwrap(),
+            Tag::LongArray(vec![1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn parses_list() {
+        assert_eq!(
+            Tag::from_snbt("[1,2,3]").unwrap(),
+            Tag::List(ListTag::from(vec![Tag::Int(1), Tag::Int(2), Tag::Int(3)]))
+        );
+    }
+
+    #[test]
+    fn byte_array_round_trips_high_bytes() {
+        // `Tag::ByteArray` stores unsigned bytes, but SNBT writes/parses them as signed `i8`s --
+        // make sure an element >= 128 survives `to_snbt`/`from_snbt` instead of erroring out.
+        let tag = Tag::ByteArray(vec![1, 200, 255]);
+        let out = tag.to_snbt();
+        assert_eq!(Tag::from_snbt(&out).unwrap(), tag);
+    }
+
+    #[test]
+    fn round_trips_compound() {
+        let src = "{name:\"Bananrama\",count:3b,list:[1,2,3],arr:[I;1,2,3]}";
+        let tag = Tag::from_snbt(src).unwrap();
+        let out = tag.to_snbt();
+        assert_eq!(Tag::from_snbt(&out).unwrap(), tag);
+    }
+
+    #[test]
+    fn writes_escaped_strings() {
+        assert_eq!(
+            Tag::String(mutf8("a\"b")).to_snbt(),
+            "\"a\\\"b\""
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! A pull-based, non-allocating NBT reader for scanning large buffers (e.g. region files) when
+//! the caller only needs a handful of fields and doesn't want to pay for a full
+//! [`CompoundTag`](crate::borrow::CompoundTag)/[`ListTag`](crate::borrow::ListTag) tree.
+//!
+//! [`EventReader`] walks the buffer one [`Event`] at a time, driven by repeated calls to
+//! [`EventReader::next`] — the same demand-next shape used by other streaming formats. Skipping a
+//! subtree the caller doesn't care about is just a matter of counting `CompoundStart`/`ListStart`
+//! against `End` until depth returns to where it started; nothing is ever copied into owned
+//! storage.
+
+use std::io::Cursor;
+
+use byteorder::ReadBytesExt;
+
+use crate::{
+    common::{
+        read_string, read_u32, BYTE_ARRAY_ID, BYTE_ID, COMPOUND_ID, DOUBLE_ID, END_ID, FLOAT_ID,
+        INT_ARRAY_ID, INT_ID, LIST_ID, LONG_ARRAY_ID, LONG_ID, MAX_DEPTH, SHORT_ID, STRING_ID,
+    },
+    Error, Mutf8Str,
+};
+
+/// A single scalar value emitted by [`EventReader`] for a `Field` event.
+#[derive(Debug, PartialEq)]
+pub enum Scalar<'a> {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(&'a Mutf8Str),
+}
+
+/// One step of the streaming walk over an NBT buffer. `CompoundStart`/`ListStart` are always
+/// matched by an `End` once every element they contain has been emitted.
+#[derive(Debug, PartialEq)]
+pub enum Event<'a> {
+    /// The start of a compound, with its name (empty inside a list).
+    CompoundStart(&'a Mutf8Str),
+    /// A named scalar field inside a compound.
+    Field(&'a Mutf8Str, Scalar<'a>),
+    /// A scalar element of a list (lists don't carry names for their elements).
+    Element(Scalar<'a>),
+    /// The start of a list, with its element type id and length.
+    ListStart(u8, u32),
+    /// The raw bytes of a `ByteArray`/`IntArray`/`LongArray`, not yet byte-swapped, with the name
+    /// it was read under (`None` inside a list, same as `Element`).
+    ArrayRef(Option<&'a Mutf8Str>, &'a [u8]),
+    /// The end of the innermost open `CompoundStart` or `ListStart`.
+    End,
+}
+
+#[derive(Clone, Copy)]
+enum Frame {
+    /// Reading the fields of a compound; ends on an `END_ID` tag.
+    Compound,
+    /// Reading `remaining` more elements of `id`, all the same type, no names.
+    List { id: u8, remaining: u32 },
+}
+
+/// A pull-based reader over an NBT buffer. Call [`next`](EventReader::next) repeatedly until it
+/// returns `Ok(None)`.
+pub struct EventReader<'a> {
+    data: Cursor<&'a [u8]>,
+    stack: Vec<Frame>,
+    started: bool,
+}
+
+impl<'a> EventReader<'a> {
+    pub fn new(data: Cursor<&'a [u8]>) -> Self {
+        EventReader {
+            data,
+            stack: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Returns the next event, or `Ok(None)` once the root compound has been fully read.
+    pub fn next(&mut self) -> Result<Option<Event<'a>>, Error> {
+        if !self.started {
+            self.started = true;
+            let root_type = self.data.read_u8().map_err(|_| Error::UnexpectedEof)?;
+            if root_type == END_ID {
+                return Ok(None);
+            }
+            if root_type != COMPOUND_ID {
+                return Err(Error::InvalidRootType(root_type));
+            }
+            let name = read_string(&mut self.data)?;
+            self.stack.push(Frame::Compound);
+            return Ok(Some(Event::CompoundStart(name)));
+        }
+
+        let Some(frame) = self.stack.last().copied() else {
+            return Ok(None);
+        };
+
+        if self.stack.len() > MAX_DEPTH {
+            return Err(Error::MaxDepthExceeded);
+        }
+
+        match frame {
+            Frame::Compound => {
+                let tag_id = self.data.read_u8().map_err(|_| Error::UnexpectedEof)?;
+                if tag_id == END_ID {
+                    self.stack.pop();
+                    return Ok(Some(Event::End));
+                }
+                let name = read_string(&mut self.data)?;
+                self.read_value(tag_id, Some(name))
+            }
+            Frame::List { id, remaining } => {
+                if remaining == 0 {
+                    self.stack.pop();
+                    return Ok(Some(Event::End));
+                }
+                *self.stack.last_mut().unwrap() = Frame::List {
+                    id,
+                    remaining: remaining - 1,
+                };
+                self.read_value(id, None)
+            }
+        }
+    }
+
+    /// Reads one value of the given tag id, producing a `Field` when `name` is `Some` (i.e. we're
+    /// inside a compound) or an `Element` when it's `None` (inside a list), and pushing a new
+    /// frame for compounds/lists.
+    fn read_value(
+        &mut self,
+        tag_id: u8,
+        name: Option<&'a Mutf8Str>,
+    ) -> Result<Option<Event<'a>>, Error> {
+        macro_rules! scalar {
+            ($read:expr, $variant:ident) => {{
+                let value = $read.map_err(|_| Error::UnexpectedEof)?;
+                Ok(Some(self.scalar_event(name, Scalar::$variant(value))))
+            }};
+        }
+
+        match tag_id {
+            BYTE_ID => scalar!(self.data.read_i8(), Byte),
+            SHORT_ID => scalar!(self.data.read_i16::<byteorder::BE>(), Short),
+            INT_ID => scalar!(self.data.read_i32::<byteorder::BE>(), Int),
+            LONG_ID => scalar!(self.data.read_i64::<byteorder::BE>(), Long),
+            FLOAT_ID => scalar!(self.data.read_f32::<byteorder::BE>(), Float),
+            DOUBLE_ID => scalar!(self.data.read_f64::<byteorder::BE>(), Double),
+            STRING_ID => {
+                let value = read_string(&mut self.data)?;
+                Ok(Some(self.scalar_event(name, Scalar::String(value))))
+            }
+            BYTE_ARRAY_ID => self.read_array_ref(name, 1),
+            INT_ARRAY_ID => self.read_array_ref(name, 4),
+            LONG_ARRAY_ID => self.read_array_ref(name, 8),
+            COMPOUND_ID => {
+                self.stack.push(Frame::Compound);
+                Ok(Some(Event::CompoundStart(name.unwrap_or(Mutf8Str::from_slice(&[])))))
+            }
+            LIST_ID => {
+                let element_id = self.data.read_u8().map_err(|_| Error::UnexpectedEof)?;
+                let len = read_u32(&mut self.data)?;
+                // always push, even for an empty list: the matching `End` is what lets a caller
+                // skip a subtree by counting `CompoundStart`/`ListStart` against `End`, regardless
+                // of whether that subtree turned out to be empty.
+                self.stack.push(Frame::List {
+                    id: element_id,
+                    remaining: len,
+                });
+                Ok(Some(Event::ListStart(element_id, len)))
+            }
+            _ => Err(Error::InvalidTagType(tag_id)),
+        }
+    }
+
+    /// Wraps a scalar as a `Field` if it was read inside a compound (`name` is `Some`), or as an
+    /// `Element` if it was read inside a list (`name` is `None`) — list elements don't have names,
+    /// so they must not be confused with a compound field that happens to be named `""`.
+    fn scalar_event(&self, name: Option<&'a Mutf8Str>, scalar: Scalar<'a>) -> Event<'a> {
+        match name {
+            Some(name) => Event::Field(name, scalar),
+            None => Event::Element(scalar),
+        }
+    }
+
+    fn read_array_ref(
+        &mut self,
+        name: Option<&'a Mutf8Str>,
+        element_size: usize,
+    ) -> Result<Option<Event<'a>>, Error> {
+        let len = read_u32(&mut self.data)? as usize;
+        let byte_len = len
+            .checked_mul(element_size)
+            .ok_or(Error::UnexpectedEof)?;
+        let start = self.data.position() as usize;
+        let slice = self
+            .data
+            .get_ref()
+            .get(start..start + byte_len)
+            .ok_or(Error::UnexpectedEof)?;
+        self.data.set_position((start + byte_len) as u64);
+        Ok(Some(Event::ArrayRef(name, slice)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{WriteBytesExt, BE};
+
+    use super::*;
+    use crate::common::write_string;
+
+    #[test]
+    fn reads_flat_compound() {
+        let mut data = Vec::new();
+        data.write_u8(COMPOUND_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b"root"));
+        data.write_u8(INT_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b"count"));
+        data.write_i32::<BE>(3).unwrap();
+        data.write_u8(END_ID).unwrap();
+
+        let mut reader = EventReader::new(Cursor::new(&data));
+        assert_eq!(
+            reader.next().unwrap(),
+            Some(Event::CompoundStart(Mutf8Str::from_slice(b"root")))
+        );
+        assert_eq!(
+            reader.next().unwrap(),
+            Some(Event::Field(
+                Mutf8Str::from_slice(b"count"),
+                Scalar::Int(3)
+            ))
+        );
+        assert_eq!(reader.next().unwrap(), Some(Event::End));
+        assert_eq!(reader.next().unwrap(), None);
+    }
+
+    #[test]
+    fn can_skip_nested_compound_by_depth() {
+        let mut data = Vec::new();
+        data.write_u8(COMPOUND_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b""));
+        data.write_u8(COMPOUND_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b"nested"));
+        data.write_u8(INT_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b"ignored"));
+        data.write_i32::<BE>(1).unwrap();
+        data.write_u8(END_ID).unwrap();
+        data.write_u8(INT_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b"after"));
+        data.write_i32::<BE>(2).unwrap();
+        data.write_u8(END_ID).unwrap();
+
+        let mut reader = EventReader::new(Cursor::new(&data));
+        reader.next().unwrap(); // root CompoundStart
+        reader.next().unwrap(); // nested CompoundStart
+
+        // skip the nested compound by counting depth
+        let mut depth = 1;
+        while depth > 0 {
+            match reader.next().unwrap().unwrap() {
+                Event::CompoundStart(_) | Event::ListStart(..) => depth += 1,
+                Event::End => depth -= 1,
+                _ => {}
+            }
+        }
+
+        assert_eq!(
+            reader.next().unwrap(),
+            Some(Event::Field(Mutf8Str::from_slice(b"after"), Scalar::Int(2)))
+        );
+    }
+
+    #[test]
+    fn empty_list_still_emits_a_matching_end() {
+        let mut data = Vec::new();
+        data.write_u8(COMPOUND_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b""));
+        data.write_u8(LIST_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b"empty"));
+        data.write_u8(END_ID).unwrap(); // element type id for an empty list
+        data.write_i32::<BE>(0).unwrap(); // length
+        data.write_u8(END_ID).unwrap();
+
+        let mut reader = EventReader::new(Cursor::new(&data));
+        reader.next().unwrap(); // root CompoundStart
+        assert_eq!(reader.next().unwrap(), Some(Event::ListStart(END_ID, 0)));
+        assert_eq!(reader.next().unwrap(), Some(Event::End));
+        assert_eq!(reader.next().unwrap(), Some(Event::End));
+        assert_eq!(reader.next().unwrap(), None);
+    }
+
+    #[test]
+    fn list_elements_are_not_fields() {
+        let mut data = Vec::new();
+        data.write_u8(COMPOUND_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b""));
+        data.write_u8(LIST_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b"list"));
+        data.write_u8(INT_ID).unwrap();
+        data.write_i32::<BE>(1).unwrap();
+        data.write_i32::<BE>(7).unwrap();
+        data.write_u8(END_ID).unwrap();
+
+        let mut reader = EventReader::new(Cursor::new(&data));
+        reader.next().unwrap(); // root CompoundStart
+        reader.next().unwrap(); // ListStart
+        assert_eq!(reader.next().unwrap(), Some(Event::Element(Scalar::Int(7))));
+        assert_eq!(reader.next().unwrap(), Some(Event::End));
+    }
+
+    #[test]
+    fn array_ref_carries_its_field_name() {
+        let mut data = Vec::new();
+        data.write_u8(COMPOUND_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b""));
+        data.write_u8(INT_ARRAY_ID).unwrap();
+        write_string(&mut data, Mutf8Str::from_slice(b"positions"));
+        data.write_u32::<BE>(1).unwrap();
+        data.write_i32::<BE>(9).unwrap();
+        data.write_u8(END_ID).unwrap();
+
+        let mut reader = EventReader::new(Cursor::new(&data));
+        reader.next().unwrap(); // root CompoundStart
+        assert_eq!(
+            reader.next().unwrap(),
+            Some(Event::ArrayRef(
+                Some(Mutf8Str::from_slice(b"positions")),
+                &9i32.to_be_bytes()
+            ))
+        );
+    }
+}